@@ -6,8 +6,10 @@
 //!
 //! # Protocol details
 //!
-//! Libp2p uses [the XX pattern](https://noiseexplorer.com/patterns/XX/). The handshake consists
-//! of three packets:
+//! Libp2p normally uses [the XX pattern](https://noiseexplorer.com/patterns/XX/); this module
+//! also supports the shorter [IK pattern](https://noiseexplorer.com/patterns/IK/) for connecting
+//! to a peer whose static noise key is already known ahead of time (see
+//! [`HandshakePattern`]). The XX handshake consists of three packets:
 //!
 //! - The initiator generates an ephemeral keypair and sends the public key to the responder.
 //! - The responder generates its own ephemeral keypair and sends the public key to the
@@ -30,7 +32,9 @@
 //!
 //! In order to use noise on top of a connection which has agreed to use noise, create a
 //! [`HandshakeInProgress`], passing a [`NoiseKey`]. This [`NoiseKey`] is typically generated at
-//! startup and doesn't need to be persisted after a restart.
+//! startup and doesn't need to be persisted after a restart. A prologue can also be passed in
+//! order to bind the handshake to data exchanged prior to it, such as the outcome of the
+//! *multistream-select* negotiation; pass an empty slice if this isn't needed.
 //!
 //! Use [`HandshakeInProgress::write_out`] to send out handshake messages ready to be sent out,
 //! and [`HandshakeInProgress::inject_data`] when data is received from the wire. At every call,
@@ -42,9 +46,23 @@
 //!
 //! Use [`Noise::inject_outbound_data`] and [`Noise::write_out`] in order to send out data to the
 //! remote, and [`Noise::inject_inbound_data`] when data is received.
-// TODO: review this last sentence, as this API might change after some experience with it
-
-use alloc::collections::VecDeque;
+//!
+//! # Known limitations
+//!
+//! - **DPI-resistant obfuscation.** Making the handshake's ephemeral keys indistinguishable from
+//!   random bytes (the obfs4/o5 pluggable-transport approach, via an Elligator2 encoding of the
+//!   ephemeral public keys) would require a custom handshake implementation driving
+//!   `curve25519-dalek` directly, since `snow` doesn't expose a way to substitute the ephemeral
+//!   keys it generates internally. Not implemented; still open.
+//! - **Independently-owned read/write halves.** Splitting a finished [`Noise`] into two halves
+//!   that don't share any state (so they can run on separate threads/tasks with no lock) isn't
+//!   possible without forking `snow`, as `snow::TransportState` only exposes
+//!   `read_message`/`write_message` on the combined state, not separately-owned per-direction
+//!   cipher state. Not implemented; still open.
+// TODO: review the "inject_outbound_data"/"write_out" sentence above, as this API might change
+//       after some experience with it
+
+use alloc::collections::{BTreeSet, VecDeque};
 use core::{cmp, convert::TryFrom as _, fmt, iter, ops};
 use libp2p::PeerId;
 use prost::Message as _;
@@ -57,6 +75,11 @@ mod payload_proto {
 /// Name of the protocol, typically used when negotiated it using *multistream-select*.
 pub const PROTOCOL_NAME: &str = "/noise";
 
+/// Prefix that gets prepended to a noise static public key before it is signed (or its
+/// signature verified) using the libp2p identity key, as mandated by the libp2p-over-noise
+/// specification.
+const NOISE_STATIC_KEY_SIGNATURE_PREFIX: &[u8] = b"noise-libp2p-static-key:";
+
 /// The noise key is the key exchanged during the noise handshake. It is **not** the same as the
 /// libp2p key. The libp2p key is used only to sign the noise public key, while the ECDH is
 /// performed with the noise key.
@@ -119,7 +142,9 @@ impl UnsignedNoiseKey {
     pub fn new() -> Self {
         UnsignedNoiseKey {
             // TODO: can panic if there's no RNG; do we care about that?
-            key: snow::Builder::new(NOISE_PARAMS.clone())
+            // The AEAD part of the params is irrelevant to key generation, which only depends on
+            // the `25519` curve; any `CipherSuite`'s params would do equally well here.
+            key: snow::Builder::new(CipherSuite::ChaChaPoly.noise_params(&HandshakePattern::Xx))
                 .generate_keypair()
                 .unwrap(),
         }
@@ -127,7 +152,7 @@ impl UnsignedNoiseKey {
 
     /// Returns the data that has to be signed.
     pub fn payload_to_sign<'a>(&'a self) -> impl Iterator<Item = impl AsRef<[u8]> + 'a> + 'a {
-        iter::once("noise-libp2p-static-key:".as_bytes()).chain(iter::once(&self.key.public[..]))
+        iter::once(NOISE_STATIC_KEY_SIGNATURE_PREFIX).chain(iter::once(&self.key.public[..]))
     }
 
     /// Returns the data that has to be signed.
@@ -166,7 +191,16 @@ impl UnsignedNoiseKey {
     }
 }
 
-/// State of the noise encryption/decryption cipher.
+/// State of the noise encryption/decryption cipher used for the remainder of the connection
+/// once the handshake (see [`HandshakeInProgress`]) has completed.
+///
+/// This is the post-handshake counterpart to [`HandshakeInProgress::inject_data`] and
+/// [`HandshakeInProgress::write_out`]: [`Noise::inject_inbound_data`] reassembles and decrypts
+/// however many length-prefixed frames are fully available in the bytes passed to it (zero,
+/// one, or more, depending on how the data happens to be chunked on the wire), while
+/// [`Noise::encrypt`] does the opposite, splitting arbitrary-length plaintext into frames no
+/// bigger than the Noise per-message ceiling (see [`Noise::MAX_PLAINTEXT_PER_FRAME`]) before
+/// encrypting and length-prefixing each one.
 pub struct Noise {
     inner: snow::TransportState,
 
@@ -175,11 +209,59 @@ pub struct Noise {
     /// once full, are immediately decoded and moved to `rx_buffer_decrypted`.
     rx_buffer_encrypted: Vec<u8>,
 
-    /// Buffer of data containing data received on the wire, after decryption.
+    /// Buffer of data containing data received on the wire, after decryption. Newly-decrypted
+    /// frames are appended at the end. Bytes before `rx_buffer_decrypted_cursor` have already
+    /// been returned through [`Noise::decoded_inbound_data`] and consumed through
+    /// [`Noise::consume_inbound_data`], but are kept around until the buffer is compacted in
+    /// order to avoid repeatedly shifting the whole buffer on every single consumed byte.
     rx_buffer_decrypted: Vec<u8>,
+
+    /// Number of bytes at the start of `rx_buffer_decrypted` that have already been consumed.
+    rx_buffer_decrypted_cursor: usize,
+
+    /// Number of frames encrypted and sent out so far. Used to trigger automatic rekeying of
+    /// the outbound direction once `rekey_after_messages` is reached. Exposed for observability
+    /// through [`Noise::message_counts`].
+    tx_messages: u64,
+
+    /// Number of frames received and decrypted so far. Used to trigger automatic rekeying of
+    /// the inbound direction once `rekey_after_messages` is reached. Exposed for observability
+    /// through [`Noise::message_counts`].
+    rx_messages: u64,
+
+    /// Value of [`Noise::DEFAULT_REKEY_AFTER_MESSAGES`] unless overridden through
+    /// [`Noise::set_rekey_threshold`].
+    rekey_after_messages: u64,
 }
 
 impl Noise {
+    /// Default number of messages sent (or received) in a single direction after which that
+    /// direction's symmetric key is automatically rotated forward using noise's `REKEY`
+    /// transform (`REKEY(k) = ENCRYPT(k, 2^64-1, "", zeros)[0..32]`). Because both peers advance
+    /// their per-direction frame counters identically, rekeying happens deterministically on
+    /// both ends without requiring any additional message on the wire.
+    ///
+    /// Note that the outbound and inbound counters, and therefore their rekeys, are completely
+    /// independent from one another: the two directions can be at different points relative to
+    /// this threshold at any given time.
+    ///
+    /// This is purely a forward-secrecy measure: `REKEY` replaces the symmetric key but, per the
+    /// Noise specification, does not reset the per-direction nonce counter, so it provides no
+    /// protection against nonce exhaustion or reuse on its own — the nonce keeps advancing
+    /// monotonically through rekeys and still wraps at `2^64` regardless of this threshold. A
+    /// connection that could plausibly approach that many frames in a single direction needs a
+    /// separate mitigation (for example closing and re-establishing the connection well before
+    /// the counter gets there); this threshold only bounds the amount of data ever encrypted
+    /// under the same key on a long-lived, high-throughput connection. Override with
+    /// [`Noise::set_rekey_threshold`].
+    const DEFAULT_REKEY_AFTER_MESSAGES: u64 = 1 << 32;
+
+    /// Maximum amount of plaintext that fits in a single Noise frame. A frame's ciphertext
+    /// (ciphertext here meaning the encrypted payload plus the 16-byte authentication tag) is prefixed
+    /// with a two-byte big-endian length and therefore cannot exceed `u16::MAX` i.e. 65535
+    /// bytes, which leaves `65535 - 16` bytes of plaintext.
+    const MAX_PLAINTEXT_PER_FRAME: usize = 65535 - 16;
+
     /// Feeds data received from the wire.
     pub fn inject_inbound_data(&mut self, mut payload: &[u8]) -> Result<usize, CipherError> {
         // As a reminder, noise frames consist of two bytes of length (big endian) followed with
@@ -190,7 +272,7 @@ impl Noise {
         loop {
             // Buffering up too much data in the output buffer should be avoided. As such, past
             // a certain threshold, return early and refuse to read more.
-            if self.rx_buffer_decrypted.len() >= 65536 * 4 {
+            if self.rx_buffer_decrypted.len() - self.rx_buffer_decrypted_cursor >= 65536 * 4 {
                 // TODO: should be configurable value ^
                 break;
             }
@@ -256,22 +338,35 @@ impl Noise {
 
             // Clear the now-decoded frame.
             self.rx_buffer_encrypted.clear();
+
+            // Rekey the inbound direction after every `rekey_after_messages` frames, to bound
+            // the amount of data ever encrypted under the same key. A threshold of `0` means
+            // automatic rekeying is disabled.
+            self.rx_messages += 1;
+            if self.rekey_after_messages != 0 && self.rx_messages % self.rekey_after_messages == 0
+            {
+                self.inner.rekey_incoming();
+            }
         }
 
         Ok(total_read)
     }
 
-    // TODO: if rx_buffer_decrypted becomes a VecDeque, this leads to a potentially weird API
-    //       where calling consume_inbound_data can lead to decoded_inbound_data to provide more
-    //       data
     pub fn decoded_inbound_data(&self) -> &[u8] {
-        &self.rx_buffer_decrypted
+        &self.rx_buffer_decrypted[self.rx_buffer_decrypted_cursor..]
     }
 
     pub fn consume_inbound_data(&mut self, n: usize) {
-        // TODO: no, slow
-        for _ in 0..n {
-            self.rx_buffer_decrypted.remove(0);
+        debug_assert!(n <= self.rx_buffer_decrypted.len() - self.rx_buffer_decrypted_cursor);
+        self.rx_buffer_decrypted_cursor += n;
+
+        // Compact the buffer once the cursor has advanced past half of it, so that the cost of
+        // shifting the remaining bytes is amortized over the bytes consumed rather than paid on
+        // every call.
+        if self.rx_buffer_decrypted_cursor >= self.rx_buffer_decrypted.len() / 2 {
+            self.rx_buffer_decrypted
+                .drain(..self.rx_buffer_decrypted_cursor);
+            self.rx_buffer_decrypted_cursor = 0;
         }
     }
 
@@ -325,7 +420,10 @@ impl Noise {
         // At least 18 bytes must be available in `destination` in order to fit an entire noise
         // frame. The check is for 19 because it doesn't make sense to send an empty frame.
         while destination.len() >= 19 {
-            let in_len = cmp::min(payload.len(), cmp::min(65536, destination.len() - 18));
+            let in_len = cmp::min(
+                payload.len(),
+                cmp::min(Self::MAX_PLAINTEXT_PER_FRAME, destination.len() - 18),
+            );
             if in_len == 0 {
                 debug_assert!(payload.is_empty());
                 break;
@@ -344,6 +442,15 @@ impl Noise {
             payload = &payload[in_len..];
             total_written += written + 2;
             destination = &mut destination[written + 2..];
+
+            // Rekey the outbound direction after every `rekey_after_messages` frames, to bound
+            // the amount of data ever encrypted under the same key. A threshold of `0` means
+            // automatic rekeying is disabled.
+            self.tx_messages += 1;
+            if self.rekey_after_messages != 0 && self.tx_messages % self.rekey_after_messages == 0
+            {
+                self.inner.rekey_outgoing();
+            }
         }
 
         (total_read, total_written)
@@ -358,7 +465,7 @@ impl Noise {
             let mut total = 0;
             let mut dest_len = destination.len();
             while dest_len >= 19 {
-                let in_len = cmp::min(65536, dest_len - 18);
+                let in_len = cmp::min(Self::MAX_PLAINTEXT_PER_FRAME, dest_len - 18);
                 total += in_len;
                 dest_len -= in_len + 18;
             }
@@ -373,6 +480,26 @@ impl Noise {
             buffer,
         }
     }
+
+    /// Overrides the number of messages after which each direction is automatically rekeyed,
+    /// in place of [`Noise::DEFAULT_REKEY_AFTER_MESSAGES`]. Pass `0` to disable automatic
+    /// rekeying entirely.
+    ///
+    /// Must be called right after the handshake completes and before any data is encrypted or
+    /// decrypted, otherwise the new threshold only applies starting from the frame counts
+    /// already reached at the time of the call.
+    pub fn set_rekey_threshold(&mut self, messages: u64) {
+        self.rekey_after_messages = messages;
+    }
+
+    /// Returns the number of frames sent and received so far, in that order, since the
+    /// handshake completed. Exposed for observability; rekeying doesn't reset these counters.
+    pub fn message_counts(&self) -> (u64, u64) {
+        (self.tx_messages, self.rx_messages)
+    }
+
+    // Splitting a finished handshake into independently-owned read/write halves is not
+    // implemented; see the "Known limitations" section of the module documentation.
 }
 
 impl fmt::Debug for Noise {
@@ -433,13 +560,19 @@ pub enum NoiseHandshake {
         /// [`PeerId`] of the remote.
         remote_peer_id: PeerId,
     },
+    /// Handshake completed cryptographically, but the remote's [`PeerId`] wasn't part of the
+    /// `accepted_peers` set passed to [`HandshakeInProgress::new`]. The connection must be
+    /// closed; no [`Noise`] cipher is made available.
+    Unauthorized,
 }
 
 /// Handshake still in progress. More data needs to be sent or received.
 pub struct HandshakeInProgress {
     /// Underlying noise state machine.
     ///
-    /// Libp2p always uses the XX handshake.
+    /// Runs either the XX or the IK pattern, depending on which [`HandshakePattern`] was passed
+    /// to [`HandshakeInProgress::new`]. The message numbers given below are those of XX; with
+    /// IK, the libp2p payload is instead carried by both of its two messages.
     ///
     /// While the `snow` library ensures that the emitted and received messages respect the
     /// handshake according to the noise specifications, libp2p extends these noise specifications
@@ -467,6 +600,17 @@ pub struct HandshakeInProgress {
     /// Buffer of data containing data waiting to be sent on the wire, after encryption. Includes
     /// the length prefixes.
     tx_buffer_encrypted: VecDeque<u8>,
+
+    /// If `Some`, the remote's [`PeerId`] is checked against this set once the handshake
+    /// completes; a remote that isn't part of it causes [`NoiseHandshake::Unauthorized`] to be
+    /// returned instead of [`NoiseHandshake::Success`]. Used to "pin" the remote identity for
+    /// private/validator connections where only a known set of peers should ever be accepted.
+    accepted_peers: Option<BTreeSet<PeerId>>,
+
+    /// Scratch buffer reused by [`HandshakeInProgress::inject_data`] to decrypt each incoming
+    /// handshake message into, instead of allocating a fresh `Vec` every time. Grown once to the
+    /// largest message seen so far and never shrunk.
+    decode_scratch_buffer: Vec<u8>,
 }
 
 enum RxPayload {
@@ -480,17 +624,62 @@ enum RxPayload {
 impl NoiseHandshake {
     /// Shortcut function that calls [`HandshakeInProgress::new`] and wraps it into a
     /// [`NoiseHandshake`].
-    pub fn new(key: &NoiseKey, is_initiator: bool) -> Self {
-        NoiseHandshake::InProgress(HandshakeInProgress::new(key, is_initiator))
+    pub fn new(
+        key: &NoiseKey,
+        is_initiator: bool,
+        cipher_suite: CipherSuite,
+        pattern: HandshakePattern,
+        prologue: &[u8],
+        accepted_peers: Option<BTreeSet<PeerId>>,
+    ) -> Self {
+        NoiseHandshake::InProgress(HandshakeInProgress::new(
+            key,
+            is_initiator,
+            cipher_suite,
+            pattern,
+            prologue,
+            accepted_peers,
+        ))
     }
 }
 
 impl HandshakeInProgress {
     /// Initializes a new noise handshake state machine.
-    pub fn new(key: &NoiseKey, is_initiator: bool) -> Self {
+    ///
+    /// The `prologue` is arbitrary data that both sides must provide identically; it is mixed
+    /// into the handshake transcript so that tampering with it causes the handshake to fail.
+    /// This is typically used to cryptographically bind the *multistream-select* negotiation
+    /// that took place prior to the noise handshake, preventing a man-in-the-middle from
+    /// downgrading the negotiated protocols. Pass an empty slice if no such binding is needed.
+    ///
+    /// `accepted_peers`, if `Some`, restricts which remote [`PeerId`]s are allowed to complete
+    /// the handshake; see [`NoiseHandshake::Unauthorized`]. Pass `None` to accept any remote,
+    /// leaving authorization entirely to the caller as before.
+    ///
+    /// `cipher_suite` selects the AEAD used to encrypt the transport once the handshake
+    /// completes; see [`CipherSuite`]. Both peers must have agreed on the same suite ahead of
+    /// time, as the Noise protocol has no way to negotiate it on the fly.
+    ///
+    /// `pattern` selects between the full XX handshake and the shorter IK handshake; see
+    /// [`HandshakePattern`]. Both peers must have agreed on the same pattern ahead of time.
+    pub fn new(
+        key: &NoiseKey,
+        is_initiator: bool,
+        cipher_suite: CipherSuite,
+        pattern: HandshakePattern,
+        prologue: &[u8],
+        accepted_peers: Option<BTreeSet<PeerId>>,
+    ) -> Self {
         let inner = {
-            let builder =
-                snow::Builder::new(NOISE_PARAMS.clone()).local_private_key(&key.key.private);
+            let builder = snow::Builder::new(cipher_suite.noise_params(&pattern))
+                .local_private_key(&key.key.private)
+                .prologue(prologue);
+            let builder = match &pattern {
+                HandshakePattern::Ik { remote_static_key } if is_initiator => {
+                    builder.remote_public_key(remote_static_key)
+                }
+                HandshakePattern::Ik { .. } | HandshakePattern::Xx => builder,
+            };
             if is_initiator {
                 builder.build_initiator()
             } else {
@@ -499,15 +688,25 @@ impl HandshakeInProgress {
             .unwrap()
         };
 
-        // Configure according to the XX handshake.
-        let (tx_payload, rx_payload, rx_messages_remain) = if is_initiator {
-            let tx = Some((1, key.handshake_message.clone().into_boxed_slice()));
-            let rx = RxPayload::NthMessage(0);
-            (tx, rx, 1)
-        } else {
-            let tx = Some((0, key.handshake_message.clone().into_boxed_slice()));
-            let rx = RxPayload::NthMessage(1);
-            (tx, rx, 2)
+        // The IK handshake is only two messages long, one from each side, and each side's
+        // single message already carries the libp2p payload; the XX handshake needs the extra
+        // round trip, and only the second and third messages carry it.
+        let (tx_payload, rx_payload, rx_messages_remain) = match pattern {
+            HandshakePattern::Xx if is_initiator => {
+                let tx = Some((1, key.handshake_message.clone().into_boxed_slice()));
+                let rx = RxPayload::NthMessage(0);
+                (tx, rx, 1)
+            }
+            HandshakePattern::Xx => {
+                let tx = Some((0, key.handshake_message.clone().into_boxed_slice()));
+                let rx = RxPayload::NthMessage(1);
+                (tx, rx, 2)
+            }
+            HandshakePattern::Ik { .. } => {
+                let tx = Some((0, key.handshake_message.clone().into_boxed_slice()));
+                let rx = RxPayload::NthMessage(0);
+                (tx, rx, 1)
+            }
         };
 
         let mut handshake = HandshakeInProgress {
@@ -517,12 +716,44 @@ impl HandshakeInProgress {
             rx_messages_remain,
             rx_buffer_encrypted: Vec::with_capacity(65536 + 2),
             tx_buffer_encrypted: VecDeque::new(),
+            accepted_peers,
+            decode_scratch_buffer: Vec::new(),
         };
 
         handshake.update_message_write();
         handshake
     }
 
+    /// Shortcut around [`HandshakeInProgress::new`] that picks [`HandshakePattern::Ik`] when
+    /// `remote_static_key` is known and we're the initiator, and falls back to
+    /// [`HandshakePattern::Xx`] otherwise (in particular, the responder side always uses XX,
+    /// since IK requires the initiator to already know the responder's key, not the other way
+    /// around).
+    pub fn new_with_known_remote_static_key(
+        key: &NoiseKey,
+        is_initiator: bool,
+        remote_static_key: Option<[u8; 32]>,
+        cipher_suite: CipherSuite,
+        prologue: &[u8],
+        accepted_peers: Option<BTreeSet<PeerId>>,
+    ) -> Self {
+        let pattern = match remote_static_key {
+            Some(remote_static_key) if is_initiator => {
+                HandshakePattern::Ik { remote_static_key }
+            }
+            _ => HandshakePattern::Xx,
+        };
+
+        HandshakeInProgress::new(
+            key,
+            is_initiator,
+            cipher_suite,
+            pattern,
+            prologue,
+            accepted_peers,
+        )
+    }
+
     /// Calls [`snow::HandshakeState::write_message`] if necessary, updates all the internal state,
     /// and puts the message into `tx_buffer_encrypted`.
     fn update_message_write(&mut self) {
@@ -591,11 +822,22 @@ impl HandshakeInProgress {
         // message to decode, which shouldn't be possible given that the handshake is finished.
         debug_assert!(self.rx_buffer_encrypted.is_empty());
 
+        if let Some(accepted_peers) = &self.accepted_peers {
+            if !accepted_peers.contains(&remote_peer_id) {
+                // `cipher` is simply dropped here, along with the keys it holds.
+                return NoiseHandshake::Unauthorized;
+            }
+        }
+
         NoiseHandshake::Success {
             cipher: Noise {
                 inner: cipher,
                 rx_buffer_encrypted: self.rx_buffer_encrypted,
                 rx_buffer_decrypted: Vec::new(), // TODO: with_capacity
+                rx_buffer_decrypted_cursor: 0,
+                tx_messages: 0,
+                rx_messages: 0,
+                rekey_after_messages: Noise::DEFAULT_REKEY_AFTER_MESSAGES,
             },
             remote_peer_id,
         }
@@ -655,25 +897,26 @@ impl HandshakeInProgress {
         }
 
         // Entire handshake message has been received.
-        // Decoding the potential payload into `decoded_payload`.
-        let decoded_payload = {
-            // The decrypted payload can only ever be smaller than the encrypted message. As such,
-            // we allocate a buffer of size equal to the encrypted message.
-            let mut decoded = vec![0; usize::from(expected_len)];
-            match self
-                .inner
-                .read_message(&self.rx_buffer_encrypted[2..], &mut decoded)
-            {
-                Err(err) => {
-                    return Err(HandshakeError::Cipher(CipherError(err)));
-                }
-                Ok(n) => {
-                    debug_assert!(n <= decoded.len());
-                    decoded.truncate(n);
-                }
+        // Decoding the potential payload into `self.decode_scratch_buffer`.
+        // The decrypted payload can only ever be smaller than the encrypted message. As such,
+        // the scratch buffer only ever needs to grow, and is reused as-is otherwise.
+        if self.decode_scratch_buffer.len() < usize::from(expected_len) {
+            self.decode_scratch_buffer
+                .resize(usize::from(expected_len), 0);
+        }
+        let decoded_payload_len = match self
+            .inner
+            .read_message(&self.rx_buffer_encrypted[2..], &mut self.decode_scratch_buffer)
+        {
+            Err(err) => {
+                return Err(HandshakeError::Cipher(CipherError(err)));
+            }
+            Ok(n) => {
+                debug_assert!(n <= self.decode_scratch_buffer.len());
+                n
             }
-            decoded
         };
+        let decoded_payload = &self.decode_scratch_buffer[..decoded_payload_len];
 
         // Data in `rx_buffer_encrypted` has been fully decoded and can be thrown away.
         self.rx_buffer_encrypted.clear();
@@ -720,11 +963,19 @@ impl HandshakeInProgress {
             // only arrive after `get_remote_static` is `Some`. Since we have already checked that
             // the payload arrives when it is supposed to, this can never panic.
             let remote_noise_static = self.inner.get_remote_static().unwrap();
-            // TODO: don't use concat() in order to not allocate a Vec
-            if !remote_public_key.verify(
-                &["noise-libp2p-static-key:".as_bytes(), remote_noise_static].concat(),
-                &handshake_payload.identity_sig,
-            ) {
+
+            // Build the prefix-concatenated-with-key message to verify without allocating a
+            // `Vec`, using a fixed-size stack array. This relies on the noise static key always
+            // being a 25519 public key, i.e. always 32 bytes, which is true of every
+            // `NoiseParams` used by this module.
+            debug_assert_eq!(remote_noise_static.len(), 32);
+            let mut to_verify = [0u8; NOISE_STATIC_KEY_SIGNATURE_PREFIX.len() + 32];
+            to_verify[..NOISE_STATIC_KEY_SIGNATURE_PREFIX.len()]
+                .copy_from_slice(NOISE_STATIC_KEY_SIGNATURE_PREFIX);
+            to_verify[NOISE_STATIC_KEY_SIGNATURE_PREFIX.len()..]
+                .copy_from_slice(remote_noise_static);
+
+            if !remote_public_key.verify(&to_verify, &handshake_payload.identity_sig) {
                 return Err(HandshakeError::SignatureVerificationFailed);
             }
 
@@ -778,9 +1029,78 @@ impl fmt::Debug for HandshakeInProgress {
     }
 }
 
+/// Choice of AEAD used to encrypt the Noise transport once the handshake has completed. The
+/// key-exchange (`25519`) and hash (`SHA256`) components of the underlying Noise protocol name
+/// are not currently configurable.
+///
+/// Both sides of a connection must agree on the same [`CipherSuite`]; if they don't, the
+/// handshake will fail to decrypt the very first encrypted message with a [`CipherError`]
+/// rather than a dedicated error, since the Noise protocol itself has no way of telling apart a
+/// wrong key from a wrong cipher. Negotiating the suite ahead of time (for example as part of
+/// the *multistream-select* protocol name) is the caller's responsibility.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// `Noise_XX_25519_ChaChaPoly_SHA256` (or `Noise_IK_25519_ChaChaPoly_SHA256` with
+    /// [`HandshakePattern::Ik`]). The default, with no particular hardware requirements.
+    ChaChaPoly,
+    /// `Noise_XX_25519_AESGCM_SHA256` (or `Noise_IK_25519_AESGCM_SHA256` with
+    /// [`HandshakePattern::Ik`]). Faster than [`CipherSuite::ChaChaPoly`] on hardware with
+    /// AES-NI acceleration.
+    Aes256Gcm,
+}
+
+impl Default for CipherSuite {
+    fn default() -> Self {
+        CipherSuite::ChaChaPoly
+    }
+}
+
+impl CipherSuite {
+    fn noise_params(&self, pattern: &HandshakePattern) -> snow::params::NoiseParams {
+        match (self, pattern) {
+            (CipherSuite::ChaChaPoly, HandshakePattern::Xx) => NOISE_PARAMS_XX_CHACHAPOLY.clone(),
+            (CipherSuite::Aes256Gcm, HandshakePattern::Xx) => NOISE_PARAMS_XX_AESGCM.clone(),
+            (CipherSuite::ChaChaPoly, HandshakePattern::Ik { .. }) => {
+                NOISE_PARAMS_IK_CHACHAPOLY.clone()
+            }
+            (CipherSuite::Aes256Gcm, HandshakePattern::Ik { .. }) => {
+                NOISE_PARAMS_IK_AESGCM.clone()
+            }
+        }
+    }
+}
+
+/// Which of the two Noise handshake patterns supported by this module to use.
+///
+/// Both sides must agree on the pattern ahead of time, the same way they must agree on the
+/// [`CipherSuite`] — for example by negotiating distinct *multistream-select* protocol names for
+/// each combination.
+#[derive(Debug, Clone)]
+pub enum HandshakePattern {
+    /// The full, 3-message mutual handshake ([XX](https://noiseexplorer.com/patterns/XX/)),
+    /// used when the remote's static public key isn't known ahead of time.
+    Xx,
+    /// The 2-message handshake ([IK](https://noiseexplorer.com/patterns/IK/)), used when the
+    /// initiator already knows the responder's static public key (for example because it was
+    /// embedded in the address that was dialed, or because of a previous connection), saving one
+    /// round-trip compared to [`HandshakePattern::Xx`].
+    Ik {
+        /// Responder's static public key. Required for the initiator to build its first
+        /// message; ignored when building the responder side, which doesn't need to know the
+        /// initiator's static key ahead of time.
+        remote_static_key: [u8; 32],
+    },
+}
+
 lazy_static::lazy_static! {
-    static ref NOISE_PARAMS: snow::params::NoiseParams =
+    static ref NOISE_PARAMS_XX_CHACHAPOLY: snow::params::NoiseParams =
         "Noise_XX_25519_ChaChaPoly_SHA256".parse().unwrap();
+    static ref NOISE_PARAMS_XX_AESGCM: snow::params::NoiseParams =
+        "Noise_XX_25519_AESGCM_SHA256".parse().unwrap();
+    static ref NOISE_PARAMS_IK_CHACHAPOLY: snow::params::NoiseParams =
+        "Noise_IK_25519_ChaChaPoly_SHA256".parse().unwrap();
+    static ref NOISE_PARAMS_IK_AESGCM: snow::params::NoiseParams =
+        "Noise_IK_25519_AESGCM_SHA256".parse().unwrap();
 }
 
 /// Potential error during the noise handshake.
@@ -805,3 +1125,182 @@ pub struct CipherError(snow::Error);
 /// Error while decoding the handshake.
 #[derive(Debug, derive_more::Display)]
 pub struct PayloadDecodeError(prost::DecodeError);
+
+/// Adapter exposing a completed [`Noise`] together with its underlying socket as a single
+/// [`AsyncRead`]/[`AsyncWrite`] object, for consumers that want to use standard async I/O
+/// combinators instead of the manual `inject_inbound_data`/`decoded_inbound_data`/
+/// `consume_inbound_data`/`encrypt`/`prepare_buffer_encryption` byte-pump API.
+///
+/// > **Note**: This requires the `std` feature, as `futures`' `AsyncRead`/`AsyncWrite` traits
+/// >           are built on top of `std::io`, which isn't available in the `no_std` build of
+/// >           this crate used by the rest of the [`noise`](super) module.
+#[cfg(feature = "std")]
+pub mod stream {
+    use super::Noise;
+    use alloc::collections::VecDeque;
+    use core::{
+        cmp,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use futures::{AsyncRead, AsyncWrite};
+    use std::io;
+
+    /// See [the module-level documentation](self).
+    pub struct NoiseStream<T> {
+        socket: T,
+        noise: Noise,
+
+        /// Scratch buffer that raw (still encrypted) bytes are read into from `socket` before
+        /// being fed to `noise`.
+        read_buffer: Box<[u8]>,
+
+        /// Plaintext accumulated across `poll_write` calls, encrypted and sent out as one or
+        /// more Noise frames (at most 64 KiB of ciphertext each, all full except possibly the
+        /// last) once `poll_flush` or `poll_close` is called, or on the next `poll_write` once
+        /// enough data has piled up to fill a frame on its own.
+        write_buffer: Vec<u8>,
+
+        /// Already-encrypted bytes (length prefix included) still waiting to be written to
+        /// `socket`. Kept across `poll_flush` calls rather than held in a local variable, so that
+        /// a `Poll::Pending` part-way through writing a frame doesn't lose the unwritten
+        /// ciphertext tail; its corresponding plaintext has already been removed from
+        /// `write_buffer` by the time it lands here, so dropping it would desync the stream.
+        tx_buffer_encrypted: VecDeque<u8>,
+    }
+
+    /// Size of the scratch buffer used to read raw bytes off the socket.
+    const READ_BUFFER_SIZE: usize = 65536;
+
+    /// Amount of plaintext in `write_buffer` at or above which the next `poll_write` first flushes
+    /// it out, without waiting for an explicit `poll_flush`. `write_buffer` can still exceed it by
+    /// up to the length of the buffer passed to a single `poll_write`. Matches the maximum
+    /// plaintext that fits in a single Noise frame (65535 bytes of ciphertext, minus the 16-byte
+    /// authentication tag).
+    const WRITE_BUFFER_FLUSH_THRESHOLD: usize = 65535 - 16;
+
+    impl<T> NoiseStream<T> {
+        /// Wraps `socket` and a just-finished [`Noise`] cipher into a single object implementing
+        /// [`AsyncRead`] and [`AsyncWrite`].
+        pub fn new(socket: T, noise: Noise) -> Self {
+            NoiseStream {
+                socket,
+                noise,
+                read_buffer: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+                write_buffer: Vec::new(),
+                tx_buffer_encrypted: VecDeque::new(),
+            }
+        }
+
+        /// Destroys the [`NoiseStream`] and returns the socket and [`Noise`] cipher it was
+        /// built from.
+        ///
+        /// Any plaintext accumulated through [`AsyncWrite::poll_write`], or ciphertext already
+        /// produced but not yet flushed, is discarded.
+        pub fn into_inner(self) -> (T, Noise) {
+            (self.socket, self.noise)
+        }
+    }
+
+    impl<T: AsyncRead + Unpin> AsyncRead for NoiseStream<T> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+
+            loop {
+                if !this.noise.decoded_inbound_data().is_empty() {
+                    let to_copy = cmp::min(buf.len(), this.noise.decoded_inbound_data().len());
+                    buf[..to_copy].copy_from_slice(&this.noise.decoded_inbound_data()[..to_copy]);
+                    this.noise.consume_inbound_data(to_copy);
+                    return Poll::Ready(Ok(to_copy));
+                }
+
+                match Pin::new(&mut this.socket).poll_read(cx, &mut this.read_buffer) {
+                    Poll::Ready(Ok(0)) => return Poll::Ready(Ok(0)),
+                    Poll::Ready(Ok(n)) => {
+                        this.noise
+                            .inject_inbound_data(&this.read_buffer[..n])
+                            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.0))?;
+                    }
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    impl<T: AsyncWrite + Unpin> AsyncWrite for NoiseStream<T> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+
+            if this.write_buffer.len() >= WRITE_BUFFER_FLUSH_THRESHOLD {
+                match Pin::new(&mut *this).poll_flush(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    // The socket can't keep up; apply backpressure instead of letting
+                    // `write_buffer` grow without bound.
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            this.write_buffer.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+
+            loop {
+                while !this.tx_buffer_encrypted.is_empty() {
+                    let (front, _) = this.tx_buffer_encrypted.as_slices();
+                    match Pin::new(&mut this.socket).poll_write(cx, front) {
+                        Poll::Ready(Ok(0)) => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::WriteZero,
+                                "socket accepted 0 bytes",
+                            )));
+                        }
+                        Poll::Ready(Ok(n)) => {
+                            this.tx_buffer_encrypted.drain(..n);
+                        }
+                        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+
+                if this.write_buffer.is_empty() {
+                    break;
+                }
+
+                // Each Noise frame adds at most 18 bytes of overhead (2-byte length prefix plus
+                // the 16-byte authentication tag), hence the `+ 18` below.
+                let mut encrypted = vec![0; cmp::min(this.write_buffer.len(), 65536) + 18];
+                let (read, written) = this
+                    .noise
+                    .encrypt(core::iter::once(&this.write_buffer[..]), &mut encrypted);
+                this.write_buffer.drain(..read);
+                encrypted.truncate(written);
+                this.tx_buffer_encrypted.extend(encrypted);
+            }
+
+            Pin::new(&mut this.socket).poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self.as_mut().poll_flush(cx) {
+                Poll::Ready(Ok(())) => {}
+                other => return other,
+            }
+
+            let this = self.get_mut();
+            Pin::new(&mut this.socket).poll_close(cx)
+        }
+    }
+}